@@ -0,0 +1,198 @@
+// Copyright (c) "Neo4j"
+// Neo4j Sweden AB [https://neo4j.com]
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// Largest payload a single Bolt chunk may carry (its length header is an
+/// unsigned 16-bit integer).
+const MAX_CHUNK_SIZE: usize = 0xFFFF;
+
+/// Frame each message in `messages` into one or more Bolt chunks and
+/// concatenate the result, terminating every message with the zero-length
+/// chunk `0x00 0x00`.
+#[pyfunction]
+fn pack_chunked(py: Python, messages: Vec<Vec<u8>>) -> PyResult<Py<PyBytes>> {
+    let mut buf = Vec::new();
+    for message in &messages {
+        for chunk in message.chunks(MAX_CHUNK_SIZE) {
+            buf.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+            buf.extend_from_slice(chunk);
+        }
+        buf.extend_from_slice(&[0, 0]);
+    }
+    Ok(PyBytes::new_bound(py, &buf).unbind())
+}
+
+/// Reassembles Bolt-chunked messages off the wire.
+///
+/// Feed it raw bytes as they arrive from the socket via [`Self::feed`], then
+/// iterate over it to drain however many complete messages are currently
+/// available. A partial chunk (or a chunk header promising more payload than
+/// has been fed so far) simply ends iteration early; feeding more data and
+/// iterating again picks up right where it left off.
+#[pyclass(module = "_rust.chunking")]
+struct ChunkedReader {
+    /// Unconsumed bytes fed in but not yet folded into a complete chunk.
+    /// Bytes before `pos` have already been decoded; they're dropped in bulk
+    /// by the next [`Self::feed`] rather than per chunk, so draining a long
+    /// run of small chunks out of one buffer is O(n) overall, not O(n^2).
+    buffer: Vec<u8>,
+    /// Read cursor into `buffer`.
+    pos: usize,
+    /// Payload of the message currently being reassembled.
+    current: Vec<u8>,
+}
+
+#[pymethods]
+impl ChunkedReader {
+    #[new]
+    fn new() -> Self {
+        ChunkedReader {
+            buffer: Vec::new(),
+            pos: 0,
+            current: Vec::new(),
+        }
+    }
+
+    /// Append freshly received bytes to the reader's internal buffer.
+    fn feed(&mut self, data: &[u8]) {
+        if self.pos == self.buffer.len() {
+            self.buffer.clear();
+        } else if self.pos > 0 {
+            self.buffer.drain(..self.pos);
+        }
+        self.pos = 0;
+        self.buffer.extend_from_slice(data);
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> Option<Py<PyBytes>> {
+        loop {
+            if self.buffer.len() - self.pos < 2 {
+                return None;
+            }
+            let len =
+                u16::from_be_bytes([self.buffer[self.pos], self.buffer[self.pos + 1]]) as usize;
+            if self.buffer.len() - self.pos < 2 + len {
+                // Payload incomplete; wait for more data.
+                return None;
+            }
+            let payload_start = self.pos + 2;
+            let payload_end = payload_start + len;
+            self.pos = payload_end;
+            if len == 0 {
+                let message = std::mem::take(&mut self.current);
+                return Some(PyBytes::new_bound(py, &message).unbind());
+            }
+            self.current
+                .extend_from_slice(&self.buffer[payload_start..payload_end]);
+        }
+    }
+}
+
+#[pyo3::pymodule]
+pub mod chunking {
+    #[pymodule_export]
+    use super::pack_chunked;
+    #[pymodule_export]
+    use super::ChunkedReader;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_chunked_frames_a_small_message() {
+        Python::with_gil(|py| {
+            let packed = pack_chunked(py, vec![vec![1, 2, 3]]).unwrap();
+            assert_eq!(packed.as_bytes(py), &[0, 3, 1, 2, 3, 0, 0]);
+        });
+    }
+
+    #[test]
+    fn pack_chunked_splits_oversized_messages() {
+        Python::with_gil(|py| {
+            let message = vec![0xAB; MAX_CHUNK_SIZE + 1];
+            let packed = pack_chunked(py, vec![message.clone()]).unwrap();
+            let bytes = packed.as_bytes(py);
+            // First chunk: max-size header + MAX_CHUNK_SIZE bytes of payload.
+            assert_eq!(&bytes[0..2], &(MAX_CHUNK_SIZE as u16).to_be_bytes());
+            // Second chunk: the one leftover byte.
+            let second_header_at = 2 + MAX_CHUNK_SIZE;
+            assert_eq!(&bytes[second_header_at..second_header_at + 2], &[0, 1]);
+            // Terminated by the zero-length chunk.
+            assert_eq!(&bytes[bytes.len() - 2..], &[0, 0]);
+        });
+    }
+
+    #[test]
+    fn pack_chunked_handles_an_empty_message() {
+        Python::with_gil(|py| {
+            let packed = pack_chunked(py, vec![vec![]]).unwrap();
+            assert_eq!(packed.as_bytes(py), &[0, 0]);
+        });
+    }
+
+    #[test]
+    fn reader_drains_concatenated_messages_in_one_feed() {
+        Python::with_gil(|py| {
+            let mut reader = ChunkedReader::new();
+            let packed = pack_chunked(py, vec![vec![1, 2], vec![3, 4, 5]]).unwrap();
+            reader.feed(packed.as_bytes(py));
+
+            assert_eq!(reader.__next__(py).unwrap().as_bytes(py), &[1, 2]);
+            assert_eq!(reader.__next__(py).unwrap().as_bytes(py), &[3, 4, 5]);
+            assert!(reader.__next__(py).is_none());
+        });
+    }
+
+    #[test]
+    fn reader_tolerates_a_partial_tail_chunk() {
+        Python::with_gil(|py| {
+            let mut reader = ChunkedReader::new();
+            let packed = pack_chunked(py, vec![vec![1, 2, 3, 4]]).unwrap();
+            let bytes = packed.as_bytes(py);
+
+            // Feed everything but the last payload byte and the terminator.
+            reader.feed(&bytes[..bytes.len() - 3]);
+            assert!(reader.__next__(py).is_none());
+
+            // The rest arrives; the message is now complete.
+            reader.feed(&bytes[bytes.len() - 3..]);
+            assert_eq!(reader.__next__(py).unwrap().as_bytes(py), &[1, 2, 3, 4]);
+        });
+    }
+
+    #[test]
+    fn reader_tolerates_a_header_split_across_feeds() {
+        Python::with_gil(|py| {
+            let mut reader = ChunkedReader::new();
+            let packed = pack_chunked(py, vec![vec![9, 9]]).unwrap();
+            let bytes = packed.as_bytes(py);
+
+            // Feed just the first byte of the 2-byte length header.
+            reader.feed(&bytes[..1]);
+            assert!(reader.__next__(py).is_none());
+
+            reader.feed(&bytes[1..]);
+            assert_eq!(reader.__next__(py).unwrap().as_bytes(py), &[9, 9]);
+        });
+    }
+}
@@ -14,15 +14,12 @@
 // limitations under the License.
 
 pub mod packstream;
+pub mod structures;
 
-use pyo3::prelude::*;
-
-use crate::make_module_in_package;
-
-pub(crate) fn register(m: &Bound<PyModule>) -> PyResult<()> {
-    let mod_packstream = make_module_in_package(m, "packstream")?;
-    packstream::register(&mod_packstream)?;
-    m.add_submodule(&mod_packstream)?;
-
-    Ok(())
+#[pyo3::pymodule]
+pub mod codec {
+    #[pymodule_export]
+    use crate::codec::packstream::packstream;
+    #[pymodule_export]
+    use crate::codec::structures::structures;
 }
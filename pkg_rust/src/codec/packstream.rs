@@ -0,0 +1,525 @@
+// Copyright (c) "Neo4j"
+// Neo4j Sweden AB [https://neo4j.com]
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use pyo3::exceptions::{PyOverflowError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyInt, PyList, PyString};
+
+const NULL: u8 = 0xC0;
+const FLOAT_64: u8 = 0xC1;
+const FALSE: u8 = 0xC2;
+const TRUE: u8 = 0xC3;
+const INT_8: u8 = 0xC8;
+const INT_16: u8 = 0xC9;
+const INT_32: u8 = 0xCA;
+const INT_64: u8 = 0xCB;
+const BYTES_8: u8 = 0xCC;
+const BYTES_16: u8 = 0xCD;
+const BYTES_32: u8 = 0xCE;
+const TINY_STRING: u8 = 0x80;
+const STRING_8: u8 = 0xD0;
+const STRING_16: u8 = 0xD1;
+const STRING_32: u8 = 0xD2;
+const TINY_LIST: u8 = 0x90;
+const LIST_8: u8 = 0xD4;
+const LIST_16: u8 = 0xD5;
+const LIST_32: u8 = 0xD6;
+const TINY_DICT: u8 = 0xA0;
+const DICT_8: u8 = 0xD8;
+const DICT_16: u8 = 0xD9;
+const DICT_32: u8 = 0xDA;
+const TINY_STRUCT: u8 = 0xB0;
+
+/// Sentinel returned by [`unpack_from`] when `data[offset..]` ends in the
+/// middle of a value. Distinguished from a decode error: the caller is
+/// expected to read more bytes and retry from the same `offset`, not to
+/// treat it as malformed input.
+#[pyclass(module = "_rust.codec.packstream", frozen)]
+pub struct Incomplete;
+
+#[pymethods]
+impl Incomplete {
+    fn __repr__(&self) -> &'static str {
+        "Incomplete"
+    }
+}
+
+/// Outcome of a single bounds-checked read during decoding.
+///
+/// Kept distinct from [`PyErr`] so that "ran out of buffer" can be handled
+/// as ordinary control flow by [`unpack_from`] instead of unwinding through
+/// `?` as an exception, while genuinely malformed input still becomes one.
+enum DecodeError {
+    Incomplete,
+    Invalid(PyErr),
+}
+
+impl From<PyErr> for DecodeError {
+    fn from(e: PyErr) -> Self {
+        DecodeError::Invalid(e)
+    }
+}
+
+type DecodeResult<T> = Result<T, DecodeError>;
+
+fn pack_value(py: Python, buf: &mut Vec<u8>, value: &Bound<PyAny>) -> PyResult<()> {
+    if value.is_none() {
+        buf.push(NULL);
+    } else if let Ok(b) = value.downcast::<pyo3::types::PyBool>() {
+        buf.push(if b.is_true() { TRUE } else { FALSE });
+    } else if value.is_instance_of::<PyInt>() {
+        // Checked separately from the float arm below: a Python int outside
+        // i64 range must not silently fall through to extract::<f64>() and
+        // get packed as a float, since that changes the value the server
+        // receives, not just its marker.
+        let i: i64 = value.extract().map_err(|_| {
+            PyOverflowError::new_err("PackStream integers must fit in a signed 64-bit value")
+        })?;
+        pack_int(buf, i);
+    } else if let Ok(f) = value.extract::<f64>() {
+        buf.push(FLOAT_64);
+        buf.extend_from_slice(&f.to_be_bytes());
+    } else if let Ok(b) = value.downcast::<PyBytes>() {
+        pack_sized(buf, b.as_bytes().len(), [BYTES_8, BYTES_16, BYTES_32])?;
+        buf.extend_from_slice(b.as_bytes());
+    } else if let Ok(s) = value.downcast::<PyString>() {
+        let s = s.to_str()?.as_bytes();
+        pack_string_header(buf, s.len())?;
+        buf.extend_from_slice(s);
+    } else if let Some((tag, fields)) = crate::codec::structures::fields_of(py, value)? {
+        pack_struct_header(buf, tag, fields.len())?;
+        for field in fields {
+            pack_value(py, buf, field.bind(py))?;
+        }
+    } else if let Ok(tuple) = value.downcast::<pyo3::types::PyTuple>() {
+        if tuple.len() == 2 {
+            if let (Ok(tag), Ok(fields)) = (
+                tuple.get_item(0)?.extract::<u8>(),
+                tuple.get_item(1)?.downcast::<PyList>().cloned(),
+            ) {
+                pack_struct_header(buf, tag, fields.len())?;
+                for field in fields.iter() {
+                    pack_value(py, buf, &field)?;
+                }
+                return Ok(());
+            }
+        }
+        Err(PyValueError::new_err(
+            "structures must be packed as a (tag: int, fields: list) tuple",
+        ))?
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        if list.len() <= 0x0F {
+            buf.push(TINY_LIST | list.len() as u8);
+        } else {
+            pack_sized(buf, list.len(), [LIST_8, LIST_16, LIST_32])?;
+        }
+        for item in list.iter() {
+            pack_value(py, buf, &item)?;
+        }
+    } else if let Ok(dict) = value.downcast::<PyDict>() {
+        pack_dict_header(buf, dict.len())?;
+        for (k, v) in dict.iter() {
+            pack_value(py, buf, &k)?;
+            pack_value(py, buf, &v)?;
+        }
+    } else {
+        return Err(PyValueError::new_err(format!(
+            "cannot pack value of type {}",
+            value.get_type().name()?
+        )));
+    }
+    Ok(())
+}
+
+fn pack_int(buf: &mut Vec<u8>, i: i64) {
+    if (-16..=127).contains(&i) {
+        buf.push(i as u8);
+    } else if i8::try_from(i).is_ok() {
+        buf.push(INT_8);
+        buf.push(i as i8 as u8);
+    } else if i16::try_from(i).is_ok() {
+        buf.push(INT_16);
+        buf.extend_from_slice(&(i as i16).to_be_bytes());
+    } else if i32::try_from(i).is_ok() {
+        buf.push(INT_32);
+        buf.extend_from_slice(&(i as i32).to_be_bytes());
+    } else {
+        buf.push(INT_64);
+        buf.extend_from_slice(&i.to_be_bytes());
+    }
+}
+
+fn pack_sized(buf: &mut Vec<u8>, len: usize, markers: [u8; 3]) -> PyResult<()> {
+    if len <= u8::MAX as usize {
+        buf.push(markers[0]);
+        buf.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        buf.push(markers[1]);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+    } else if len <= u32::MAX as usize {
+        buf.push(markers[2]);
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+    } else {
+        return Err(PyOverflowError::new_err("PackStream size exceeds 2^32 - 1"));
+    }
+    Ok(())
+}
+
+fn pack_string_header(buf: &mut Vec<u8>, len: usize) -> PyResult<()> {
+    if len <= 0x0F {
+        buf.push(TINY_STRING | len as u8);
+        Ok(())
+    } else {
+        pack_sized(buf, len, [STRING_8, STRING_16, STRING_32])
+    }
+}
+
+fn pack_dict_header(buf: &mut Vec<u8>, len: usize) -> PyResult<()> {
+    if len <= 0x0F {
+        buf.push(TINY_DICT | len as u8);
+        Ok(())
+    } else {
+        pack_sized(buf, len, [DICT_8, DICT_16, DICT_32])
+    }
+}
+
+fn pack_struct_header(buf: &mut Vec<u8>, tag: u8, len: usize) -> PyResult<()> {
+    if len > 0x0F {
+        return Err(PyOverflowError::new_err(
+            "PackStream structures support at most 15 fields",
+        ));
+    }
+    buf.push(TINY_STRUCT | len as u8);
+    buf.push(tag);
+    Ok(())
+}
+
+/// Read `n` bytes starting at `*offset`, advancing it on success.
+///
+/// Returns [`DecodeError::Incomplete`] rather than an exception when `data`
+/// doesn't hold `n` more bytes, so incremental callers can tell "malformed"
+/// apart from "just needs more data".
+fn read_bytes<'a>(data: &'a [u8], offset: &mut usize, n: usize) -> DecodeResult<&'a [u8]> {
+    let end = offset.checked_add(n).ok_or(DecodeError::Incomplete)?;
+    let slice = data.get(*offset..end).ok_or(DecodeError::Incomplete)?;
+    *offset = end;
+    Ok(slice)
+}
+
+fn read_u8(data: &[u8], offset: &mut usize) -> DecodeResult<u8> {
+    Ok(read_bytes(data, offset, 1)?[0])
+}
+
+fn read_len(data: &[u8], offset: &mut usize, marker: u8) -> DecodeResult<usize> {
+    Ok(match marker {
+        m if m == STRING_8 || m == LIST_8 || m == DICT_8 || m == BYTES_8 => {
+            read_u8(data, offset)? as usize
+        }
+        m if m == STRING_16 || m == LIST_16 || m == DICT_16 || m == BYTES_16 => {
+            u16::from_be_bytes(read_bytes(data, offset, 2)?.try_into().unwrap()) as usize
+        }
+        m if m == STRING_32 || m == LIST_32 || m == DICT_32 || m == BYTES_32 => {
+            u32::from_be_bytes(read_bytes(data, offset, 4)?.try_into().unwrap()) as usize
+        }
+        _ => unreachable!("read_len called with non-sized marker"),
+    })
+}
+
+/// Decode exactly one top-level value starting at `*offset`, advancing it
+/// past the bytes consumed. Bounds-checked throughout: running off the end
+/// of `data` yields [`DecodeError::Incomplete`] instead of panicking or
+/// raising, so this can be driven incrementally as more bytes arrive.
+fn try_unpack_value(py: Python, data: &[u8], offset: &mut usize) -> DecodeResult<PyObject> {
+    let marker = read_u8(data, offset)?;
+    Ok(match marker {
+        NULL => py.None(),
+        FALSE => false.into_py(py),
+        TRUE => true.into_py(py),
+        FLOAT_64 => {
+            f64::from_be_bytes(read_bytes(data, offset, 8)?.try_into().unwrap()).into_py(py)
+        }
+        INT_8 => (read_bytes(data, offset, 1)?[0] as i8 as i64).into_py(py),
+        INT_16 => {
+            i16::from_be_bytes(read_bytes(data, offset, 2)?.try_into().unwrap()).into_py(py)
+        }
+        INT_32 => {
+            i32::from_be_bytes(read_bytes(data, offset, 4)?.try_into().unwrap()).into_py(py)
+        }
+        INT_64 => {
+            i64::from_be_bytes(read_bytes(data, offset, 8)?.try_into().unwrap()).into_py(py)
+        }
+        m @ (BYTES_8 | BYTES_16 | BYTES_32) => {
+            let len = read_len(data, offset, m)?;
+            PyBytes::new_bound(py, read_bytes(data, offset, len)?).into_py(py)
+        }
+        m if (TINY_STRING..=TINY_STRING | 0x0F).contains(&m) => {
+            unpack_string(py, data, offset, (m & 0x0F) as usize)?
+        }
+        m @ (STRING_8 | STRING_16 | STRING_32) => {
+            let len = read_len(data, offset, m)?;
+            unpack_string(py, data, offset, len)?
+        }
+        m if (TINY_LIST..=TINY_LIST | 0x0F).contains(&m) => {
+            unpack_list(py, data, offset, (m & 0x0F) as usize)?
+        }
+        m @ (LIST_8 | LIST_16 | LIST_32) => {
+            let len = read_len(data, offset, m)?;
+            unpack_list(py, data, offset, len)?
+        }
+        m if (TINY_DICT..=TINY_DICT | 0x0F).contains(&m) => {
+            unpack_dict(py, data, offset, (m & 0x0F) as usize)?
+        }
+        m @ (DICT_8 | DICT_16 | DICT_32) => {
+            let len = read_len(data, offset, m)?;
+            unpack_dict(py, data, offset, len)?
+        }
+        m if (TINY_STRUCT..=TINY_STRUCT | 0x0F).contains(&m) => {
+            let len = (m & 0x0F) as usize;
+            let tag = read_u8(data, offset)?;
+            let mut fields = Vec::with_capacity(len);
+            for _ in 0..len {
+                fields.push(try_unpack_value(py, data, offset)?);
+            }
+            match crate::codec::structures::construct(py, tag, fields.clone())
+                .map_err(DecodeError::Invalid)?
+            {
+                Some(value) => value,
+                // No driver type registered for this tag (yet): fall back to
+                // the raw (tag, fields) tuple the Python layer used to get
+                // for every structure.
+                None => (tag, fields).into_py(py),
+            }
+        }
+        m if (0..=0x7F).contains(&m) => (m as i64).into_py(py),
+        m if (0xF0..=0xFF).contains(&m) => ((m as i8) as i64).into_py(py),
+        m => {
+            return Err(DecodeError::Invalid(PyValueError::new_err(format!(
+                "unknown PackStream marker {m:#04x}"
+            ))))
+        }
+    })
+}
+
+fn unpack_string(py: Python, data: &[u8], offset: &mut usize, len: usize) -> DecodeResult<PyObject> {
+    let bytes = read_bytes(data, offset, len)?;
+    let text = std::str::from_utf8(bytes).map_err(|e| {
+        DecodeError::Invalid(PyValueError::new_err(format!(
+            "invalid UTF-8 in PackStream string: {e}"
+        )))
+    })?;
+    Ok(PyString::new_bound(py, text).into_py(py))
+}
+
+fn unpack_list(py: Python, data: &[u8], offset: &mut usize, len: usize) -> DecodeResult<PyObject> {
+    // Every item takes at least one byte, so don't trust `len` beyond that.
+    let mut items = Vec::with_capacity(len.min(data.len().saturating_sub(*offset)));
+    for _ in 0..len {
+        items.push(try_unpack_value(py, data, offset)?);
+    }
+    Ok(PyList::new_bound(py, items).into_py(py))
+}
+
+fn unpack_dict(py: Python, data: &[u8], offset: &mut usize, len: usize) -> DecodeResult<PyObject> {
+    let dict = PyDict::new_bound(py);
+    for _ in 0..len {
+        let key = try_unpack_value(py, data, offset)?;
+        let value = try_unpack_value(py, data, offset)?;
+        dict.set_item(key, value)
+            .map_err(DecodeError::Invalid)?;
+    }
+    Ok(dict.into_py(py))
+}
+
+/// Serialize a single Python value to its PackStream representation.
+#[pyfunction]
+fn pack(py: Python, value: &Bound<PyAny>) -> PyResult<Py<PyBytes>> {
+    let mut buf = Vec::new();
+    pack_value(py, &mut buf, value)?;
+    Ok(PyBytes::new_bound(py, &buf).unbind())
+}
+
+/// Deserialize a single PackStream value from `data`.
+///
+/// `data` must contain exactly one complete, top-level PackStream value.
+#[pyfunction]
+fn unpack(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    let mut offset = 0usize;
+    try_unpack_value(py, data, &mut offset).map_err(|e| match e {
+        DecodeError::Incomplete => PyValueError::new_err("unexpected end of PackStream buffer"),
+        DecodeError::Invalid(e) => e,
+    })
+}
+
+/// Incrementally decode exactly one top-level value from `data[offset:]`.
+///
+/// Returns `(value, new_offset)` on success. If `data` ends mid-value, this
+/// returns [`Incomplete`] rather than raising: the caller should read more
+/// bytes and call this again with the *same* `offset`. Genuinely malformed
+/// input still raises. Drive this in a loop to drain multiple values out of
+/// one buffer without copying or reallocating it between calls.
+#[pyfunction]
+fn unpack_from(py: Python, data: &[u8], offset: usize) -> PyResult<PyObject> {
+    let mut pos = offset;
+    match try_unpack_value(py, data, &mut pos) {
+        Ok(value) => Ok((value, pos).into_py(py)),
+        Err(DecodeError::Incomplete) => Ok(Incomplete.into_py(py)),
+        Err(DecodeError::Invalid(e)) => Err(e),
+    }
+}
+
+#[pymodule]
+pub mod packstream {
+    #[pymodule_export]
+    use super::{pack, unpack, unpack_from, Incomplete};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(py: Python, value: &Bound<PyAny>) -> PyObject {
+        let packed = pack(py, value).unwrap();
+        unpack(py, packed.as_bytes(py)).unwrap()
+    }
+
+    #[test]
+    fn roundtrips_scalars() {
+        Python::with_gil(|py| {
+            for i in [
+                0_i64, -1, -16, -17, 127, 128, -128, -129, 32767, 32768, -32768, -32769,
+                i32::MAX as i64,
+                i32::MAX as i64 + 1,
+                i32::MIN as i64,
+                i32::MIN as i64 - 1,
+                i64::MAX,
+                i64::MIN,
+            ] {
+                let value = i.into_py(py);
+                let result = roundtrip(py, value.bind(py));
+                assert_eq!(result.extract::<i64>(py).unwrap(), i, "int {i}");
+            }
+
+            let value = 1.5_f64.into_py(py);
+            let result = roundtrip(py, value.bind(py));
+            assert_eq!(result.extract::<f64>(py).unwrap(), 1.5);
+
+            assert!(roundtrip(py, true.into_py(py).bind(py)).extract::<bool>(py).unwrap());
+            assert!(roundtrip(py, py.None().bind(py)).is_none(py));
+        });
+    }
+
+    #[test]
+    fn rejects_ints_that_overflow_i64() {
+        Python::with_gil(|py| {
+            let too_big = py.eval_bound("2 ** 64", None, None).unwrap();
+            assert!(pack(py, &too_big).is_err());
+        });
+    }
+
+    #[test]
+    fn roundtrips_strings_across_marker_size_boundaries() {
+        Python::with_gil(|py| {
+            for len in [0usize, 15, 16, 255, 256, 65535, 65536] {
+                let s = "a".repeat(len);
+                let value = s.clone().into_py(py);
+                let result = roundtrip(py, value.bind(py));
+                assert_eq!(result.extract::<String>(py).unwrap(), s, "string len {len}");
+            }
+        });
+    }
+
+    #[test]
+    fn roundtrips_lists_across_marker_size_boundaries() {
+        Python::with_gil(|py| {
+            for len in [0usize, 15, 16, 255, 256] {
+                let list = PyList::new_bound(py, vec![0_i64; len]);
+                let result = roundtrip(py, list.as_any());
+                assert_eq!(result.extract::<Vec<i64>>(py).unwrap().len(), len);
+            }
+        });
+    }
+
+    #[test]
+    fn roundtrips_dicts_and_bytes() {
+        Python::with_gil(|py| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("a", 1).unwrap();
+            dict.set_item("b", 2).unwrap();
+            let result = roundtrip(py, dict.as_any());
+            let result = result.bind(py).downcast::<PyDict>().unwrap();
+            assert_eq!(result.get_item("a").unwrap().unwrap().extract::<i64>().unwrap(), 1);
+
+            let bytes = PyBytes::new_bound(py, &[1, 2, 3]);
+            let result = roundtrip(py, bytes.as_any());
+            assert_eq!(result.extract::<Vec<u8>>(py).unwrap(), vec![1, 2, 3]);
+        });
+    }
+
+    #[test]
+    fn unpack_from_returns_incomplete_on_a_truncated_buffer() {
+        Python::with_gil(|py| {
+            // STRING_16 marker promising 2 bytes of string, but none follow.
+            let data = [STRING_16, 0, 2];
+            let result = unpack_from(py, &data, 0).unwrap();
+            assert!(result.bind(py).downcast::<Incomplete>().is_ok());
+        });
+    }
+
+    #[test]
+    fn unpack_from_still_raises_on_malformed_input() {
+        Python::with_gil(|py| {
+            // 0xC5 is not a PackStream marker at all: this is bad data, not
+            // a truncated buffer, so it must raise rather than return
+            // Incomplete.
+            let data = [0xC5];
+            assert!(unpack_from(py, &data, 0).is_err());
+        });
+    }
+
+    #[test]
+    fn unpack_from_drains_multiple_values_from_one_buffer() {
+        Python::with_gil(|py| {
+            let mut data = pack(py, 1_i64.into_py(py).bind(py)).unwrap().as_bytes(py).to_vec();
+            data.extend_from_slice(pack(py, 2_i64.into_py(py).bind(py)).unwrap().as_bytes(py));
+
+            let mut offset = 0usize;
+            let mut values = Vec::new();
+            loop {
+                let result = unpack_from(py, &data, offset).unwrap();
+                let result = result.bind(py);
+                if result.downcast::<Incomplete>().is_ok() {
+                    break;
+                }
+                let (value, new_offset): (i64, usize) = result.extract().unwrap();
+                values.push(value);
+                offset = new_offset;
+            }
+            assert_eq!(values, vec![1, 2]);
+        });
+    }
+
+    #[test]
+    fn roundtrips_generic_structures_as_tag_fields_tuples() {
+        Python::with_gil(|py| {
+            let tag = 0x4E_u8; // 'N', unregistered in this test
+            let fields = PyList::new_bound(py, vec![1_i64, 2_i64]);
+            let value = (tag, fields).into_py(py);
+            let result = roundtrip(py, value.bind(py));
+            let (got_tag, got_fields): (u8, Vec<i64>) = result.extract(py).unwrap();
+            assert_eq!(got_tag, tag);
+            assert_eq!(got_fields, vec![1, 2]);
+        });
+    }
+}
@@ -0,0 +1,272 @@
+// Copyright (c) "Neo4j"
+// Neo4j Sweden AB [https://neo4j.com]
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tag-to-type registry for the well-known PackStream structures Neo4j uses
+//! for graph, temporal and spatial values (`Node`, `Relationship`, `Path`,
+//! `Date`, `Time`, `LocalTime`, the `DateTime` family, `Duration`,
+//! `Point2D`/`Point3D`).
+//!
+//! The driver registers, once at start-up, a `(type, constructor,
+//! to_fields)` triple per tag byte. Decoding a registered structure then
+//! calls straight into `constructor(*fields)` instead of handing back a
+//! generic `(tag, fields)` tuple for the Python layer to translate field by
+//! field, and packing a registered instance calls `to_fields(value)` once
+//! to get the field list back out. Everything else about the structure
+//! (lengths, nested values, markers) still goes through the regular codec.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use pyo3::prelude::*;
+use pyo3::types::{PyTuple, PyType};
+
+/// Tags for the well-known Bolt structures the driver wants native handling
+/// for. See the Bolt PackStream structure semantics spec for the full list;
+/// these are the graph, temporal and spatial ones.
+pub mod tags {
+    pub const NODE: u8 = b'N';
+    pub const RELATIONSHIP: u8 = b'R';
+    pub const PATH: u8 = b'P';
+    pub const DATE: u8 = b'D';
+    pub const TIME: u8 = b'T';
+    pub const LOCAL_TIME: u8 = b't';
+    pub const DATE_TIME_ZONE_OFFSET: u8 = b'F';
+    pub const DATE_TIME_ZONE_ID: u8 = b'f';
+    pub const LOCAL_DATE_TIME: u8 = b'd';
+    pub const DURATION: u8 = b'E';
+    pub const POINT_2D: u8 = b'X';
+    pub const POINT_3D: u8 = b'Y';
+}
+
+struct StructureHandler {
+    py_type: Py<PyType>,
+    constructor: Py<PyAny>,
+    to_fields: Py<PyAny>,
+}
+
+#[derive(Default)]
+struct Registry {
+    by_tag: HashMap<u8, StructureHandler>,
+    /// Index from a registered type's identity (its `PyTypeObject*`, stable
+    /// for the type's lifetime) to its tag. Looked up with the value's exact
+    /// `type()`, never `isinstance`: the driver's spatial `Point` types are
+    /// related by inheritance, and a subclass sweep over a `HashMap` (whose
+    /// iteration order isn't meaningful) would pick a nondeterministic, and
+    /// potentially wrong, tag for them.
+    by_type: HashMap<usize, u8>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(Registry::default()))
+}
+
+/// Register the Python type used for PackStream structures tagged `tag`.
+///
+/// `constructor(*fields)` must build a `py_type` instance from the
+/// already-decoded field values, and `to_fields(instance)` must return them
+/// back out as a list in the same order, ready to be packed. Intended to be
+/// called once per tag at driver start-up; a later call for the same tag
+/// replaces the previous registration.
+#[pyfunction]
+pub(crate) fn register_structure(
+    py: Python,
+    tag: u8,
+    py_type: Py<PyType>,
+    constructor: Py<PyAny>,
+    to_fields: Py<PyAny>,
+) {
+    let type_key = py_type.bind(py).as_ptr() as usize;
+    let mut registry = registry().lock().unwrap();
+    // Drop the replaced type's index entry so it no longer packs under `tag`.
+    if let Some(old) = registry.by_tag.get(&tag) {
+        let old_key = old.py_type.bind(py).as_ptr() as usize;
+        if registry.by_type.get(&old_key) == Some(&tag) {
+            registry.by_type.remove(&old_key);
+        }
+    }
+    registry.by_type.insert(type_key, tag);
+    registry.by_tag.insert(
+        tag,
+        StructureHandler {
+            py_type,
+            constructor,
+            to_fields,
+        },
+    );
+}
+
+/// If `tag` has a registered handler, build the Python object it describes
+/// from `fields`. Returns `Ok(None)` for an unregistered tag so the caller
+/// can fall back to a generic `(tag, fields)` tuple.
+///
+/// The registered constructor is cloned out of the registry before it is
+/// called, and the lock is released before that call: `constructor` runs
+/// arbitrary Python, which may itself decode or encode a (possibly
+/// different) registered structure and re-enter this module, and
+/// `std::sync::Mutex` is not reentrant.
+pub(crate) fn construct(py: Python, tag: u8, fields: Vec<PyObject>) -> PyResult<Option<PyObject>> {
+    let constructor = {
+        let registry = registry().lock().unwrap();
+        match registry.by_tag.get(&tag) {
+            Some(handler) => handler.constructor.clone_ref(py),
+            None => return Ok(None),
+        }
+    };
+    let args = PyTuple::new_bound(py, fields);
+    Ok(Some(constructor.bind(py).call1(args)?.unbind()))
+}
+
+/// If `value`'s exact type is registered, return its tag and field values as
+/// packed by its `to_fields` callback. Returns `Ok(None)` for any value whose
+/// exact type isn't registered, so the caller can fall back to packing it as
+/// whatever plain PackStream type it otherwise looks like.
+///
+/// Dispatches on `type(value)` identity rather than `isinstance`, so a
+/// subclass of a registered type (e.g. a driver `Point` subtype) is never
+/// silently packed under its base class's tag.
+///
+/// The `to_fields` handle is cloned out of the registry and the lock
+/// released before it is called, for the same reentrancy reason as
+/// [`construct`]: it calls into arbitrary Python.
+pub(crate) fn fields_of(py: Python, value: &Bound<PyAny>) -> PyResult<Option<(u8, Vec<PyObject>)>> {
+    let type_key = value.get_type().as_ptr() as usize;
+    let found = {
+        let registry = registry().lock().unwrap();
+        registry.by_type.get(&type_key).and_then(|&tag| {
+            registry
+                .by_tag
+                .get(&tag)
+                .map(|handler| (tag, handler.to_fields.clone_ref(py)))
+        })
+    };
+    let Some((tag, to_fields)) = found else {
+        return Ok(None);
+    };
+    let fields = to_fields
+        .bind(py)
+        .call1((value,))?
+        .extract::<Vec<PyObject>>()?;
+    Ok(Some((tag, fields)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    /// Defines a `Point(x, y)` class plus a `to_fields` function in a fresh
+    /// namespace and registers `Point` under `tag`, returning the namespace
+    /// so tests can also pull out `Point` and any subclass they define.
+    fn setup_point(py: Python, tag: u8) -> Bound<'_, PyDict> {
+        let ns = PyDict::new_bound(py);
+        py.run_bound(
+            r#"
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+class SubPoint(Point):
+    pass
+
+def to_fields(p):
+    return [p.x, p.y]
+"#,
+            None,
+            Some(&ns),
+        )
+        .unwrap();
+        let point_type: Py<PyType> = ns.get_item("Point").unwrap().unwrap().extract().unwrap();
+        let to_fields = ns.get_item("to_fields").unwrap().unwrap().unbind();
+        register_structure(py, tag, point_type.clone_ref(py), point_type.into_py(py), to_fields);
+        ns
+    }
+
+    #[test]
+    fn construct_calls_the_registered_constructor_with_decoded_fields() {
+        Python::with_gil(|py| {
+            let tag = 0xF0;
+            setup_point(py, tag);
+            let fields = vec![1_i64.into_py(py), 2_i64.into_py(py)];
+            let point = construct(py, tag, fields).unwrap().unwrap();
+            let point = point.bind(py);
+            assert_eq!(point.getattr("x").unwrap().extract::<i64>().unwrap(), 1);
+            assert_eq!(point.getattr("y").unwrap().extract::<i64>().unwrap(), 2);
+        });
+    }
+
+    #[test]
+    fn construct_returns_none_for_an_unregistered_tag() {
+        Python::with_gil(|py| {
+            assert!(construct(py, 0xAB, vec![]).unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn fields_of_round_trips_through_to_fields() {
+        Python::with_gil(|py| {
+            let tag = 0xF1;
+            let ns = setup_point(py, tag);
+            let point_type = ns.get_item("Point").unwrap().unwrap();
+            let point = point_type.call1((3, 4)).unwrap();
+
+            let (got_tag, fields) = fields_of(py, &point).unwrap().unwrap();
+            assert_eq!(got_tag, tag);
+            let fields: Vec<i64> = fields.into_iter().map(|f| f.extract(py).unwrap()).collect();
+            assert_eq!(fields, vec![3, 4]);
+        });
+    }
+
+    #[test]
+    fn fields_of_does_not_match_a_subclass_by_isinstance() {
+        Python::with_gil(|py| {
+            let tag = 0xF2;
+            let ns = setup_point(py, tag);
+            let sub_point_type = ns.get_item("SubPoint").unwrap().unwrap();
+            let sub_point = sub_point_type.call1((3, 4)).unwrap();
+
+            // SubPoint is an instance of Point, but wasn't registered itself,
+            // so it must not be packed under Point's tag.
+            assert!(fields_of(py, &sub_point).unwrap().is_none());
+        });
+    }
+}
+
+#[pyo3::pymodule]
+pub mod structures {
+    #[pymodule_export]
+    use super::register_structure;
+
+    /// Exposes the `tags` constants as module attributes so the driver
+    /// registers structures against the same tag bytes this module decodes
+    /// against, instead of hard-coding its own copy of them.
+    #[pymodule_init]
+    fn init(m: &Bound<'_, pyo3::types::PyModule>) -> PyResult<()> {
+        m.add("NODE", super::tags::NODE)?;
+        m.add("RELATIONSHIP", super::tags::RELATIONSHIP)?;
+        m.add("PATH", super::tags::PATH)?;
+        m.add("DATE", super::tags::DATE)?;
+        m.add("TIME", super::tags::TIME)?;
+        m.add("LOCAL_TIME", super::tags::LOCAL_TIME)?;
+        m.add("DATE_TIME_ZONE_OFFSET", super::tags::DATE_TIME_ZONE_OFFSET)?;
+        m.add("DATE_TIME_ZONE_ID", super::tags::DATE_TIME_ZONE_ID)?;
+        m.add("LOCAL_DATE_TIME", super::tags::LOCAL_DATE_TIME)?;
+        m.add("DURATION", super::tags::DURATION)?;
+        m.add("POINT_2D", super::tags::POINT_2D)?;
+        m.add("POINT_3D", super::tags::POINT_3D)?;
+        Ok(())
+    }
+}
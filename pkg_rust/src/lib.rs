@@ -13,37 +13,20 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod chunking;
 pub mod codec;
 
-use pyo3::prelude::*;
-
 /// A Python module implemented in Rust.
-#[pymodule]
-#[pyo3(name = "_rust")]
-fn packstream(m: &Bound<PyModule>) -> PyResult<()> {
-    let mod_codec = make_module_in_package(m, "codec")?;
-    codec::register(&mod_codec)?;
-    m.add_submodule(&mod_codec)?;
-
-    Ok(())
-}
-
-fn make_module_in_package<'py>(
-    parent_module: &Bound<'py, PyModule>,
-    submodule_name: &str,
-) -> PyResult<Bound<'py, PyModule>> {
-    let py = parent_module.py();
-
-    let submodule = PyModule::new_bound(py, submodule_name)?;
-    let full_name = format!("{}.{}", parent_module.name()?, submodule_name);
-    parent_module.add_submodule(&submodule)?;
-
-    // hack to make python pick up the submodule as a package
-    // https://github.com/PyO3/pyo3/issues/1517#issuecomment-808664021
-    submodule.setattr("__name__", &full_name)?;
-    py.import_bound("sys")?
-        .getattr("modules")?
-        .set_item(&full_name, &submodule)?;
-
-    Ok(submodule)
+///
+/// Declared as a declarative `#[pymodule] mod` so that `codec` and its own
+/// nested submodules are registered as proper packages: PyO3 takes care of
+/// `sys.modules` registration and gives every class defined in a nested
+/// module the correct `__module__` (e.g. `_rust.codec.packstream`), instead
+/// of the `builtins` it would get from a plain function-based module.
+#[pyo3::pymodule]
+mod _rust {
+    #[pymodule_export]
+    use crate::codec::codec;
+    #[pymodule_export]
+    use crate::chunking::chunking;
 }